@@ -0,0 +1,201 @@
+//! An interactive command loop for walking and tweaking a parsed `ClockTree`,
+//! in the spirit of a classic `run_debugger_command` dispatcher: each call takes
+//! one command line and reports whether the caller should keep looping.
+
+use crate::regs::SplitField;
+use crate::{ClockTree, ParseError};
+
+fn pll_offset(idx: usize) -> usize {
+    if idx < 3 {
+        0x1c0 + idx * 0x20
+    } else {
+        0x220
+    }
+}
+
+/// Decode the raw M/P/S (and K, for the dithering PLL at index 3) fields of PLL
+/// `idx` directly out of the register buffer.
+fn decode_pll_fields(buf: &[u8; 0x400], idx: usize) -> (u16, u8, u8, Option<u16>) {
+    let o = pll_offset(idx);
+    let m_high_bits = if idx < 3 { 2 } else { 1 };
+    let m = SplitField::new(o, o + 4, m_high_bits).read(buf);
+    let p = buf[o + 8] & 0x3f;
+    let s = buf[o + 0xc] & 0x7;
+    let k = if idx == 3 {
+        Some(SplitField::new(o + 0x20, o + 0x24, 4).read(buf))
+    } else {
+        None
+    };
+    (m, p, s, k)
+}
+
+fn parse_num(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Interactive session over a register buffer: commands inspect the decoded
+/// `ClockTree`, and `set` patches the buffer and recomputes it in place.
+pub struct Debugger {
+    buf: [u8; 0x400],
+    xtal_hz: u32,
+    tree: ClockTree,
+    last_command: Option<String>,
+}
+
+impl Debugger {
+    pub fn new(buf: [u8; 0x400], xtal_hz: u32) -> Result<Debugger, ParseError> {
+        let tree = ClockTree::parse(&buf, xtal_hz)?;
+        Ok(Debugger {
+            buf,
+            xtal_hz,
+            tree,
+            last_command: None,
+        })
+    }
+
+    /// Run one command line (a bare-Enter line repeats the last non-empty command).
+    /// Returns `false` when the session should stop.
+    pub fn run_debugger_command(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let line = if line.is_empty() {
+            match self.last_command.clone() {
+                Some(prev) => prev,
+                None => return true,
+            }
+        } else {
+            self.last_command = Some(line.to_string());
+            line.to_string()
+        };
+
+        let mut parts = line.split_whitespace();
+        let cmd = match parts.next() {
+            Some(cmd) => cmd,
+            None => return true,
+        };
+
+        match cmd {
+            "pll" => self.cmd_pll(parts.next()),
+            "clk" => self.cmd_clk(parts.next()),
+            "set" => self.cmd_set(parts.next(), parts.next()),
+            "dump" => self.cmd_dump(parts.next()),
+            "help" => self.cmd_help(),
+            "quit" | "exit" => return false,
+            _ => println!("Unknown command: {} (try \"help\")", cmd),
+        }
+        true
+    }
+
+    fn cmd_help(&self) {
+        println!("Commands:");
+        println!("  pll N          decode PLL N's M/P/S/K fields and rates");
+        println!("  clk NAME       trace clkgen NAME back through its mux to a PLL or the crystal");
+        println!("  set OFFSET BYTE  patch buf[OFFSET] = BYTE and recompute rates");
+        println!("  dump FILE      write the current buffer out to FILE");
+        println!("  quit | exit    leave the debugger");
+        println!("  <Enter>        repeat the last command");
+    }
+
+    fn cmd_pll(&self, arg: Option<&str>) {
+        let idx = match arg.and_then(parse_num) {
+            Some(idx) if idx < self.tree.plls.len() => idx,
+            _ => {
+                println!("Usage: pll N, where N is 0..=3");
+                return;
+            }
+        };
+
+        let pll = self.tree.plls[idx];
+        let (m, p, s, k) = decode_pll_fields(&self.buf, idx);
+        print!("PLL{}: M={} P={} S={}", idx, m, p, s);
+        if let Some(k) = k {
+            print!(" K={}", k);
+        }
+        println!();
+        println!(
+            "  generated={} Hz output={} Hz bypassed={} reset={}",
+            pll.generated_hz, pll.output_hz, pll.bypassed, pll.reset
+        );
+    }
+
+    fn cmd_clk(&self, arg: Option<&str>) {
+        let name = match arg {
+            Some(name) => name,
+            None => {
+                println!("Usage: clk NAME");
+                return;
+            }
+        };
+
+        let gen = match self.tree.clkgens.iter().find(|gen| gen.name == name) {
+            Some(gen) => gen,
+            None => {
+                println!("No such clkgen: {}", name);
+                return;
+            }
+        };
+
+        println!(
+            "clkgen \"{}\": {} Hz ({})",
+            gen.name,
+            gen.rate_hz,
+            if gen.on { "ON" } else { "OFF" }
+        );
+        if gen.mux_src == self.tree.plls.len() {
+            println!("  <- crystal ({} Hz)", self.xtal_hz);
+        } else if let Some(pll) = self.tree.plls.get(gen.mux_src) {
+            println!(
+                "  <- PLL{} (generated={} Hz output={} Hz)",
+                gen.mux_src, pll.generated_hz, pll.output_hz
+            );
+        } else {
+            println!("  Warning: mux {} is outside known range.", gen.mux_src);
+        }
+        if let Some(div) = gen.divider {
+            println!("  /{}", div);
+        }
+    }
+
+    fn cmd_set(&mut self, offset_arg: Option<&str>, value_arg: Option<&str>) {
+        let (offset, value) = match (offset_arg.and_then(parse_num), value_arg.and_then(parse_num))
+        {
+            (Some(offset), Some(value)) if offset < self.buf.len() && value <= 0xff => {
+                (offset, value as u8)
+            }
+            _ => {
+                println!("Usage: set OFFSET BYTE (OFFSET < 0x400, BYTE <= 0xff)");
+                return;
+            }
+        };
+
+        let previous = self.buf[offset];
+        self.buf[offset] = value;
+        match ClockTree::parse(&self.buf, self.xtal_hz) {
+            Ok(tree) => {
+                self.tree = tree;
+                println!("buf[{:#x}] = {:#04x}", offset, value);
+            }
+            Err(e) => {
+                self.buf[offset] = previous;
+                println!("Warning: {} (byte left unchanged)", e);
+            }
+        }
+    }
+
+    fn cmd_dump(&self, arg: Option<&str>) {
+        let path = match arg {
+            Some(path) => path,
+            None => {
+                println!("Usage: dump FILE");
+                return;
+            }
+        };
+
+        match std::fs::write(path, self.buf) {
+            Ok(()) => println!("Wrote {} bytes to {}", self.buf.len(), path),
+            Err(e) => println!("Failed to write {}: {}", path, e),
+        }
+    }
+}
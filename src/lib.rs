@@ -0,0 +1,496 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+#[cfg(target_os = "linux")]
+pub mod mem;
+
+pub mod debugger;
+mod regs;
+
+use regs::{ClkGenCtl, DivCtl, MuxSel, PllCtl, DITHER_PLL_K, DITHER_PLL_M, SIMPLE_PLL_M};
+
+fn pll_simple_rate(
+    buf: &[u8],
+    inrate: u32,
+    has_outdiv: bool,
+    pll: usize,
+) -> Result<u32, ParseError> {
+    if buf.len() != 0x20 {
+        panic!("buf must be 0x20 bytes wide for simple PLLs");
+    }
+    let m = u64::from(SIMPLE_PLL_M.read(buf));
+    let p = u64::from(buf[8] & 0x3f);
+    let s = u32::from(buf[0xc] & 0x7);
+    let ctl = PllCtl(buf[0x10]);
+
+    if ctl.bypass() {
+        return Ok(inrate);
+    }
+    if ctl.reset() {
+        return Ok(0);
+    }
+    if p == 0 {
+        return Err(ParseError::ZeroPllDivider { pll });
+    }
+    // Saturate rather than panic: a rate past u32::MAX means the dump is
+    // bogus or mid-reprogramming, not something worth crashing over.
+    let rate = u32::try_from((u64::from(inrate) * m) / (p * 2u64.pow(s))).unwrap_or(u32::MAX);
+    Ok(if has_outdiv {
+        let outdiv = DivCtl(buf[0x1c]);
+        if outdiv.bypass() {
+            rate
+        } else {
+            rate / u32::from(outdiv.divisor())
+        }
+    } else {
+        rate
+    })
+}
+
+fn pll_dither_rate(buf: &[u8], inrate: u32, pll: usize) -> Result<u32, ParseError> {
+    if buf.len() != 0x30 {
+        panic!("buf must be 0x30 bytes wide for dithering PLLs");
+    }
+    let m = u64::from(DITHER_PLL_M.read(buf));
+    let p = u64::from(buf[8] & 0x3f);
+    let s = u32::from(buf[0xc] & 0x7);
+    let k = u64::from(DITHER_PLL_K.read(buf));
+    let ctl = PllCtl(buf[0x10]);
+
+    if ctl.bypass() {
+        return Ok(inrate);
+    }
+    if ctl.reset() {
+        return Ok(0);
+    }
+    if p == 0 {
+        return Err(ParseError::ZeroPllDivider { pll });
+    }
+    let num = u64::from(inrate) * (m * 1024 + k);
+    let denom = p * 2u64.pow(s);
+    // The "+ 511" part rounds to the nearest Hz; saturate on overflow rather
+    // than panic, as in pll_simple_rate.
+    Ok(u32::try_from((num / denom + 511) / 1024).unwrap_or(u32::MAX))
+}
+
+/// Chosen (m, p, s, outdiv, achieved_hz, error_hz) fields for a simple PLL, plus the
+/// VCO rate used to break ties between equally-good candidates.
+type SimplePllCandidate = (u16, u8, u8, Option<u8>, u32, i32, u64);
+
+/// Search P/S/(outdiv) for the combination whose ideal M comes closest to `target`,
+/// write the winning fields into `buf`, and return them along with the achieved rate
+/// and its error in Hz. Ties are broken towards the lower VCO rate (`inrate*M/P`).
+/// Also clears the PLL's reset/bypass bits and the output divider's bypass bit, so
+/// that feeding `buf` back through `pll_simple_rate` reproduces `achieved`
+/// regardless of what those bits were set to beforehand.
+pub fn pll_simple_solve(
+    buf: &mut [u8],
+    inrate: u32,
+    target: u32,
+    has_outdiv: bool,
+) -> (u16, u8, u8, Option<u8>, u32, i32) {
+    if buf.len() != 0x20 {
+        panic!("buf must be 0x20 bytes wide for simple PLLs");
+    }
+
+    let (outdiv_lo, outdiv_hi) = if has_outdiv { (2u64, 31u64) } else { (1u64, 1u64) };
+
+    let mut best: Option<SimplePllCandidate> = None;
+
+    for p in 1u64..=63 {
+        for s in 0u32..=7 {
+            let shift = p * 2u64.pow(s);
+            for outdiv in outdiv_lo..=outdiv_hi {
+                let ideal_m = (u64::from(target) * shift * outdiv + u64::from(inrate) / 2)
+                    / u64::from(inrate);
+                let m = ideal_m.min(1023);
+                let vco = u64::from(inrate) * m / p;
+                let achieved = match u32::try_from(vco / 2u64.pow(s) / outdiv) {
+                    Ok(rate) => rate,
+                    Err(_) => continue,
+                };
+                let error = i64::from(achieved) - i64::from(target);
+
+                let better = match &best {
+                    None => true,
+                    Some((.., best_err, best_vco)) => {
+                        error.unsigned_abs() < i64::from(*best_err).unsigned_abs()
+                            || (error.unsigned_abs() == i64::from(*best_err).unsigned_abs()
+                                && vco < *best_vco)
+                    }
+                };
+                if better {
+                    best = Some((
+                        m as u16,
+                        p as u8,
+                        s as u8,
+                        has_outdiv.then_some(outdiv as u8),
+                        achieved,
+                        error as i32,
+                        vco,
+                    ));
+                }
+            }
+        }
+    }
+
+    let (m, p, s, outdiv, achieved, error, _) = best.expect("P/S/outdiv search space is never empty");
+
+    SIMPLE_PLL_M.write(buf, m);
+    buf[8] = (buf[8] & !0x3f) | (p & 0x3f);
+    buf[0xc] = (buf[0xc] & !0x7) | (s & 0x7);
+    buf[0x10] = PllCtl(buf[0x10]).enabled().0;
+    if let Some(outdiv) = outdiv {
+        buf[0x1c] = DivCtl(buf[0x1c]).with_divisor(outdiv).0;
+    }
+
+    (m, p, s, outdiv, achieved, error)
+}
+
+/// Chosen (m, p, s, k, achieved_hz, error_hz) fields for a dithering PLL, plus the
+/// VCO rate used to break ties between equally-good candidates.
+type DitherPllCandidate = (u16, u8, u8, u16, u32, i32, u64);
+
+/// Same idea as `pll_simple_solve` but for the dithering PLL's fractional M/K pair:
+/// the ideal `M*1024+K` is computed for each P/S, then split into the 9-bit M and
+/// 12-bit K fields (K absorbing whatever M's 9-bit width can't hold, up to K's own
+/// limit of 4095). Also clears
+/// the PLL's reset/bypass bits, as in `pll_simple_solve`.
+pub fn pll_dither_solve(buf: &mut [u8], inrate: u32, target: u32) -> (u16, u8, u8, u16, u32, i32) {
+    if buf.len() != 0x30 {
+        panic!("buf must be 0x30 bytes wide for dithering PLLs");
+    }
+
+    let mut best: Option<DitherPllCandidate> = None;
+
+    for p in 1u64..=63 {
+        for s in 0u32..=7 {
+            let shift = p * 2u64.pow(s);
+            let ideal_mk =
+                (u64::from(target) * shift * 1024 + u64::from(inrate) / 2) / u64::from(inrate);
+            let m = (ideal_mk / 1024).min(511);
+            let k = (ideal_mk - m * 1024).min(4095);
+
+            let num = u64::from(inrate) * (m * 1024 + k);
+            // The "+ 511" part rounds to the nearest Hz, as in pll_dither_rate.
+            let achieved = match u32::try_from((num / shift + 511) / 1024) {
+                Ok(rate) => rate,
+                Err(_) => continue,
+            };
+            let error = i64::from(achieved) - i64::from(target);
+            let vco = u64::from(inrate) * m / p;
+
+            let better = match &best {
+                None => true,
+                Some((.., best_err, best_vco)) => {
+                    error.unsigned_abs() < i64::from(*best_err).unsigned_abs()
+                        || (error.unsigned_abs() == i64::from(*best_err).unsigned_abs()
+                            && vco < *best_vco)
+                }
+            };
+            if better {
+                best = Some((m as u16, p as u8, s as u8, k as u16, achieved, error as i32, vco));
+            }
+        }
+    }
+
+    let (m, p, s, k, achieved, error, _) = best.expect("P/S search space is never empty");
+
+    DITHER_PLL_M.write(buf, m);
+    buf[8] = (buf[8] & !0x3f) | (p & 0x3f);
+    buf[0xc] = (buf[0xc] & !0x7) | (s & 0x7);
+    buf[0x10] = PllCtl(buf[0x10]).enabled().0;
+    DITHER_PLL_K.write(buf, k);
+
+    (m, p, s, k, achieved, error)
+}
+
+fn clkgen_rate(ctl: u8, divctl: Option<u8>, srcs: &[u32], bypass: bool) -> (bool, u32) {
+    let ctl = ClkGenCtl(ctl);
+    let on = ctl.enabled();
+    let inrate = match ctl.mux() {
+        MuxSel::Pll(n) => srcs[usize::from(n)],
+        MuxSel::Crystal => srcs[4],
+        MuxSel::Unknown(sel) => {
+            eprintln!("Warning: mux {} is outside known range.", sel);
+            0
+        }
+    };
+
+    if let Some(divctl) = divctl {
+        let div = DivCtl(divctl);
+        if bypass {
+            return (on, inrate);
+        }
+        if div.divisor() < 2 {
+            eprintln!("Warning: divider value is less than 2.");
+            return (on, 0);
+        }
+        (on, inrate / u32::from(div.divisor()))
+    } else {
+        (on, inrate)
+    }
+}
+
+fn axigate_is_on(ctl: u8, bit: u8) -> bool {
+    ctl & (1 << bit) != 0
+}
+
+/// One of the four on-chip PLLs after being decoded from a register dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pll {
+    pub generated_hz: u32,
+    pub output_hz: u32,
+    pub bypassed: bool,
+    pub reset: bool,
+}
+
+/// A clock generator: a mux selecting one of the PLLs (or the crystal), followed by
+/// an optional integer divider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClkGen {
+    pub name: &'static str,
+    pub on: bool,
+    pub mux_src: usize,
+    pub divider: Option<u8>,
+    pub rate_hz: u32,
+}
+
+/// An AXI bus clock gate: a single enable bit in one of the gate control registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AxiGate {
+    pub name: &'static str,
+    pub on: bool,
+}
+
+/// Everything that can go wrong while decoding a CLKRESET register dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The PLL global bypass bit (offset 0x34, bit 0) is set. Its effect on the
+    /// clock generators downstream is undocumented, so we refuse to guess.
+    GlobalBypassSet,
+    /// A PLL's 6-bit P divider field is 0 while the PLL is neither bypassed
+    /// nor held in reset. P=0 has no meaning (the forward rate would require
+    /// dividing by zero), so the dump is either bogus or mid-reprogramming.
+    ZeroPllDivider { pll: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::GlobalBypassSet => write!(
+                f,
+                "PLL global bypass bit is set; its effect on clock generators is unknown"
+            ),
+            ParseError::ZeroPllDivider { pll } => {
+                write!(f, "PLL{} has a P divider of 0 while neither bypassed nor reset", pll)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The fully decoded clock tree: four PLLs, the clock generators they feed, and the
+/// AXI bus gates downstream of those.
+pub struct ClockTree {
+    pub plls: [Pll; 4],
+    pub clkgens: Vec<ClkGen>,
+    pub axigates: Vec<AxiGate>,
+}
+
+impl ClockTree {
+    /// Decode a 0x400-byte CLKRESET register dump (`0x904b0000`-`0x904b03ff`) taken
+    /// with a crystal running at `xtal_hz`.
+    pub fn parse(buf: &[u8; 0x400], xtal_hz: u32) -> Result<ClockTree, ParseError> {
+        let mut plls = [Pll {
+            generated_hz: 0,
+            output_hz: 0,
+            bypassed: false,
+            reset: false,
+        }; 4];
+        for (i, pll) in plls.iter_mut().enumerate().take(3) {
+            let o = 0x1c0 + i * 0x20;
+            let has_outdiv = i == 0 || i == 1;
+            let ctl = PllCtl(buf[o + 0x10]);
+            pll.generated_hz = pll_simple_rate(&buf[o..(o + 0x20)], xtal_hz, has_outdiv, i)?;
+            pll.bypassed = ctl.bypass();
+            pll.reset = ctl.reset();
+        }
+        let ctl = PllCtl(buf[0x220 + 0x10]);
+        plls[3].generated_hz = pll_dither_rate(&buf[0x220..0x250], xtal_hz, 3)?;
+        plls[3].bypassed = ctl.bypass();
+        plls[3].reset = ctl.reset();
+
+        let pllmask = buf[0x38];
+        for (i, pll) in plls.iter_mut().enumerate() {
+            pll.output_hz = if (pllmask & (1 << i)) != 0 {
+                xtal_hz
+            } else {
+                pll.generated_hz
+            };
+        }
+
+        if (buf[0x34] & 0x1) != 0 {
+            return Err(ParseError::GlobalBypassSet);
+        }
+
+        let pllmux = [
+            plls[0].output_hz,
+            plls[1].output_hz,
+            plls[2].output_hz,
+            plls[3].output_hz,
+            xtal_hz,
+        ];
+        let clkgen_specs: [(&str, u8, Option<u8>); 20] = [
+            ("axi", buf[0x40], Some(buf[0x4c])),
+            ("a9dp", buf[0x80], Some(buf[0x84])),
+            ("l2cc", buf[0x90], Some(buf[0x94])),
+            ("tpi", buf[0xa0], Some(buf[0xa4])),
+            ("csys", buf[0xb0], Some(buf[0xb4])),
+            ("extphy0", buf[0xc0], Some(buf[0xc4])),
+            ("extphy1", buf[0xd0], Some(buf[0xd4])),
+            ("extphy2", buf[0xe0], Some(buf[0xe4])),
+            ("ddr", buf[0xf0], Some(buf[0xf4])),
+            ("pfe", buf[0x100], Some(buf[0x104])),
+            ("ipsec", buf[0x110], Some(buf[0x114])),
+            ("dect", buf[0x120], Some(buf[0x124])),
+            ("gemtx", buf[0x130], Some(buf[0x134])),
+            ("tdmntg", buf[0x140], Some(buf[0x144])),
+            ("tsuntg", buf[0x150], Some(buf[0x154])),
+            ("sata_pmu", buf[0x160], Some(buf[0x164])),
+            ("sata_oob", buf[0x170], Some(buf[0x174])),
+            ("sata_occ", buf[0x180], Some(buf[0x184])),
+            ("pcie_occ", buf[0x190], Some(buf[0x194])),
+            ("sgmii_occ", buf[0x1a0], Some(buf[0x1a4])),
+        ];
+        let mut clkgens = Vec::with_capacity(clkgen_specs.len());
+        for (name, ctl, divctl) in clkgen_specs {
+            let (on, rate_hz) = clkgen_rate(ctl, divctl, &pllmux, false);
+            let mux_src = match ClkGenCtl(ctl).mux() {
+                MuxSel::Pll(n) => usize::from(n),
+                MuxSel::Crystal => 4,
+                MuxSel::Unknown(sel) => usize::from(sel),
+            };
+            clkgens.push(ClkGen {
+                name,
+                on,
+                mux_src,
+                divider: divctl.map(|d| DivCtl(d).divisor()),
+                rate_hz,
+            });
+        }
+
+        let axigate_specs: [(&str, u8, u8); 20] = [
+            ("0_4", buf[0x40], 4),
+            ("dpi_cie", buf[0x40], 5),
+            ("dpi_decomp", buf[0x40], 6),
+            ("0_7", buf[0x40], 7),
+            ("dus", buf[0x44], 0),
+            ("ipsec_eape", buf[0x44], 1),
+            ("ipsec_spacc", buf[0x44], 2),
+            ("pfe_sys", buf[0x44], 3),
+            ("tdm", buf[0x44], 4),
+            ("i2cspi", buf[0x44], 5),
+            ("uart", buf[0x44], 6),
+            ("rtc", buf[0x44], 7),
+            ("pcie0", buf[0x48], 0),
+            ("pcie1", buf[0x48], 1),
+            ("sata", buf[0x48], 2),
+            ("usb0", buf[0x48], 3),
+            ("usb1", buf[0x48], 4),
+            ("2_5", buf[0x48], 5),
+            ("2_6", buf[0x48], 6),
+            ("2_7", buf[0x48], 7),
+        ];
+        let axigates = axigate_specs
+            .into_iter()
+            .map(|(name, ctl, bit)| AxiGate {
+                name,
+                on: axigate_is_on(ctl, bit),
+            })
+            .collect();
+
+        Ok(ClockTree {
+            plls,
+            clkgens,
+            axigates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XTAL_HZ: u32 = 24_000_000;
+
+    #[test]
+    fn simple_solve_roundtrips_without_outdiv() {
+        let mut buf = [0u8; 0x20];
+        let (.., achieved, _error) = pll_simple_solve(&mut buf, XTAL_HZ, 600_000_000, false);
+        assert_eq!(pll_simple_rate(&buf, XTAL_HZ, false, 0).unwrap(), achieved);
+    }
+
+    #[test]
+    fn simple_solve_roundtrips_with_outdiv() {
+        let mut buf = [0u8; 0x20];
+        let (.., achieved, _error) = pll_simple_solve(&mut buf, XTAL_HZ, 100_000_000, true);
+        assert_eq!(pll_simple_rate(&buf, XTAL_HZ, true, 0).unwrap(), achieved);
+    }
+
+    #[test]
+    fn dither_solve_roundtrips() {
+        let mut buf = [0u8; 0x30];
+        let (.., achieved, _error) = pll_dither_solve(&mut buf, XTAL_HZ, 491_520_000);
+        assert_eq!(pll_dither_rate(&buf, XTAL_HZ, 0).unwrap(), achieved);
+    }
+
+    #[test]
+    fn simple_solve_clamps_m_to_10_bits() {
+        let mut buf = [0u8; 0x20];
+        // Every candidate whose rounded ideal M fits in 10 bits overshoots
+        // u32::MAX and is skipped, so the search should settle on a clamped,
+        // widest M (10 bits: 0..=1023) rather than panicking or wrapping.
+        let (m, ..) = pll_simple_solve(&mut buf, XTAL_HZ, u32::MAX, false);
+        assert_eq!(m, 1023);
+    }
+
+    #[test]
+    fn dither_solve_clamps_m_to_9_bits() {
+        let mut buf = [0u8; 0x30];
+        let (m, ..) = pll_dither_solve(&mut buf, XTAL_HZ, u32::MAX);
+        assert_eq!(m, 511);
+    }
+
+    #[test]
+    fn simple_solve_breaks_ties_towards_lower_vco() {
+        let mut buf = [0u8; 0x20];
+        // Every (p, s) with m = p (s=1) or m = p/2 (s=0, p even) reproduces
+        // XTAL_HZ / 2 exactly, so this target has many zero-error candidates.
+        // The solver should pick the one with the lowest VCO (`inrate*m/p`).
+        let (m, p, s, _outdiv, achieved, error) =
+            pll_simple_solve(&mut buf, XTAL_HZ, XTAL_HZ / 2, false);
+        assert_eq!(error, 0);
+        assert_eq!(achieved, XTAL_HZ / 2);
+        let vco = u64::from(XTAL_HZ) * u64::from(m) / u64::from(p);
+        assert_eq!(vco, u64::from(XTAL_HZ) / 2);
+        assert_eq!(s, 0);
+    }
+
+    #[test]
+    fn simple_solve_clears_stale_reset_and_bypass_bits() {
+        let mut buf = [0u8; 0x20];
+        buf[0x10] = 0x11; // reset + bypass both set before solving
+        let (.., achieved, _error) = pll_simple_solve(&mut buf, XTAL_HZ, 600_000_000, false);
+        assert_eq!(pll_simple_rate(&buf, XTAL_HZ, false, 0).unwrap(), achieved);
+    }
+
+    #[test]
+    fn simple_solve_clears_stale_outdiv_bypass_bit() {
+        let mut buf = [0u8; 0x20];
+        buf[0x1c] = 0x80; // output divider bypass set before solving
+        let (.., achieved, _error) = pll_simple_solve(&mut buf, XTAL_HZ, 100_000_000, true);
+        assert_eq!(pll_simple_rate(&buf, XTAL_HZ, true, 0).unwrap(), achieved);
+    }
+}
@@ -0,0 +1,77 @@
+//! Live register capture straight out of `/dev/mem`, for boards where a pre-dumped
+//! file isn't available. Only wired up on Linux, where `/dev/mem` and `mmap(2)`
+//! behave as expected.
+
+use std::ffi::CString;
+use std::io;
+use std::ptr;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// Physical base address of the CLKRESET register region.
+pub const CLKRESET_BASE: u64 = 0x904b0000;
+/// Size in bytes of the CLKRESET register region.
+pub const CLKRESET_LEN: usize = 0x400;
+
+/// mmap a physical address range out of `/dev/mem` and copy it into a `Vec`.
+///
+/// `phys_addr` need not be page-aligned: the mapping is rounded down to the
+/// containing page and `len` bytes are read back out starting at the original
+/// address.
+pub fn read_phys(phys_addr: u64, len: usize) -> io::Result<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let page_base = phys_addr & !(PAGE_SIZE - 1);
+    let page_offset = (phys_addr - page_base) as usize;
+    let map_len = page_offset + len;
+
+    let path = CString::new("/dev/mem").expect("path has no interior NUL");
+    // SAFETY: `path` is a valid NUL-terminated C string and stays alive for the call.
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_SYNC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: `fd` is open for reading, `map_len` is non-zero, and `page_base` is
+    // page-aligned by construction, satisfying mmap(2)'s offset requirement.
+    let map = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            map_len,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            fd,
+            page_base as libc::off_t,
+        )
+    };
+    let mmap_errno = io::Error::last_os_error();
+    // SAFETY: `fd` was returned by the `open` call above and is still valid.
+    unsafe {
+        libc::close(fd);
+    }
+    if map == libc::MAP_FAILED {
+        return Err(mmap_errno);
+    }
+
+    // SAFETY: `map` is a valid mapping of at least `map_len` bytes, and
+    // `page_offset..page_offset + len` lies within that range.
+    let data =
+        unsafe { std::slice::from_raw_parts(map.cast::<u8>().add(page_offset), len).to_vec() };
+
+    // SAFETY: `map`/`map_len` are exactly the pointer and length used to create
+    // the mapping above.
+    unsafe {
+        libc::munmap(map, map_len);
+    }
+
+    Ok(data)
+}
+
+/// Read the live CLKRESET register dump out of `/dev/mem`.
+pub fn read_clkreset_live() -> io::Result<[u8; CLKRESET_LEN]> {
+    let data = read_phys(CLKRESET_BASE, CLKRESET_LEN)?;
+    let mut buf = [0u8; CLKRESET_LEN];
+    buf.copy_from_slice(&data);
+    Ok(buf)
+}